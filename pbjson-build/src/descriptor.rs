@@ -0,0 +1,188 @@
+//! Indexes the [`FileDescriptorProto`]s registered with a [`Builder`](crate::Builder) so
+//! that they can be looked up by fully-qualified type name and iterated in a stable,
+//! package-grouped order
+
+use std::fmt::{Display, Formatter};
+use std::io::{Error, ErrorKind, Result};
+
+use prost::Message as _;
+use prost_types::{DescriptorProto, EnumDescriptorProto, FileDescriptorProto, FileDescriptorSet};
+
+use crate::options::RawOverrides;
+
+/// A fully-qualified protobuf package, e.g. `.mypackage.v1`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Package(String);
+
+impl Package {
+    pub fn new(package: impl Into<String>) -> Self {
+        Self(package.into())
+    }
+}
+
+impl Display for Package {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.trim_start_matches('.'))
+    }
+}
+
+/// The fully-qualified path of a protobuf type, e.g. `mypackage.v1.MyMessage`
+#[derive(Debug, Clone)]
+pub struct TypePath {
+    package: Package,
+    path: String,
+}
+
+impl TypePath {
+    fn new(package: Package, path: String) -> Self {
+        Self { package, path }
+    }
+
+    /// The package this type belongs to
+    pub fn package(&self) -> &Package {
+        &self.package
+    }
+
+    /// The fully-qualified path of this type, e.g. `mypackage.v1.MyMessage`
+    ///
+    /// The package part is written as [`Package`]'s `Display` writes it, without a
+    /// leading dot; a type in the empty package is written as `.MyMessage`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// A protobuf message or enum descriptor, as registered with a [`DescriptorSet`]
+#[derive(Debug, Clone)]
+pub enum Descriptor {
+    Enum(EnumDescriptorProto),
+    Message(DescriptorProto),
+}
+
+/// An index of the [`FileDescriptorProto`]s registered with a [`Builder`](crate::Builder),
+/// keyed by the fully-qualified name of the messages and enums they contain
+#[derive(Debug, Default)]
+pub struct DescriptorSet {
+    files: Vec<FileDescriptorProto>,
+    types: Vec<(TypePath, Descriptor)>,
+    overrides: RawOverrides,
+}
+
+impl DescriptorSet {
+    /// Register an encoded [`FileDescriptorSet`]
+    ///
+    /// Unlike [`Self::register_file_descriptor`], this also makes any `(pbjson.*)`
+    /// in-proto option set in the registered files visible through [`Self::overrides`],
+    /// since - unlike an already-decoded [`FileDescriptorProto`] - the raw bytes still
+    /// carry the resolved extension values that `prost_types` doesn't know how to decode
+    pub fn register_encoded(&mut self, descriptors: &[u8]) -> Result<()> {
+        let set = FileDescriptorSet::decode(descriptors)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        self.overrides.collect_from_file_descriptor_set(descriptors);
+
+        for file in set.file {
+            self.register_file_descriptor(file);
+        }
+        Ok(())
+    }
+
+    /// Register a single encoded [`FileDescriptorProto`]
+    ///
+    /// Like [`Self::register_encoded`], this makes `(pbjson.*)` in-proto options set in
+    /// `file` visible through [`Self::overrides`]
+    pub fn register_encoded_file_descriptor(&mut self, file: &[u8]) -> Result<()> {
+        let file_descriptor =
+            FileDescriptorProto::decode(file).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        self.overrides.collect_from_file_descriptor(file);
+        self.register_file_descriptor(file_descriptor);
+        Ok(())
+    }
+
+    /// The `(pbjson.*)` in-proto option overrides recovered from every
+    /// [`Self::register_encoded`]/[`Self::register_encoded_file_descriptor`] call so far
+    pub fn overrides(&self) -> &RawOverrides {
+        &self.overrides
+    }
+
+    /// Register a single already-decoded [`FileDescriptorProto`]
+    ///
+    /// Note this does *not* make any `(pbjson.*)` in-proto options set in `file` visible
+    /// through [`Self::overrides`] - by the time `file` is a decoded
+    /// [`FileDescriptorProto`], `prost_types` has already discarded the extension
+    /// field(s) they were resolved to, with no raw bytes left for [`Self::overrides`] to
+    /// read back. Prefer [`Self::register_encoded`]/[`Self::register_encoded_file_descriptor`]
+    /// when in-proto options matter and the encoded bytes are available
+    pub fn register_file_descriptor(&mut self, file: FileDescriptorProto) {
+        let package = Package::new(format!(".{}", file.package()));
+
+        for message in &file.message_type {
+            self.register_message(&package, message);
+        }
+        for e in &file.enum_type {
+            let path = format!("{}.{}", package, e.name());
+            self.types
+                .push((TypePath::new(package.clone(), path), Descriptor::Enum(e.clone())));
+        }
+
+        self.files.push(file);
+    }
+
+    fn register_message(&mut self, package: &Package, message: &DescriptorProto) {
+        let path = format!("{}.{}", package, message.name());
+        self.types.push((
+            TypePath::new(package.clone(), path.clone()),
+            Descriptor::Message(message.clone()),
+        ));
+
+        for nested in &message.nested_type {
+            self.register_nested(package, &path, nested);
+        }
+        for e in &message.enum_type {
+            let nested_path = format!("{}.{}", path, e.name());
+            self.types.push((
+                TypePath::new(package.clone(), nested_path),
+                Descriptor::Enum(e.clone()),
+            ));
+        }
+    }
+
+    fn register_nested(&mut self, package: &Package, parent_path: &str, message: &DescriptorProto) {
+        // Skip synthetic map entry messages, they are handled inline by `message::resolve_message`
+        if message
+            .options
+            .as_ref()
+            .map(|o| o.map_entry())
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let path = format!("{}.{}", parent_path, message.name());
+        self.types.push((
+            TypePath::new(package.clone(), path.clone()),
+            Descriptor::Message(message.clone()),
+        ));
+
+        for nested in &message.nested_type {
+            self.register_nested(package, &path, nested);
+        }
+        for e in &message.enum_type {
+            let nested_path = format!("{}.{}", path, e.name());
+            self.types.push((
+                TypePath::new(package.clone(), nested_path),
+                Descriptor::Enum(e.clone()),
+            ));
+        }
+    }
+
+    /// Iterate over the registered types, grouped by package (in package name order),
+    /// in registration order within each package
+    pub fn iter(&self) -> impl Iterator<Item = (&TypePath, &Descriptor)> {
+        let mut types: Vec<_> = self.types.iter().collect();
+        // A stable sort, so types of the same package keep their registration order
+        types.sort_by(|(a, _), (b, _)| a.package.cmp(&b.package));
+        types.into_iter().map(|(path, descriptor)| (path, descriptor))
+    }
+}
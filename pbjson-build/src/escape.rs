@@ -0,0 +1,20 @@
+//! Helpers for escaping identifiers that collide with Rust keywords
+
+/// Prefixes `s` with `r#` if `s` is a Rust keyword, otherwise returns `s` unchanged
+///
+/// This mirrors the behaviour of `prost-build`, which generates raw identifiers
+/// for fields and variants whose name happens to match a reserved word
+pub fn escape_ident(s: &str) -> String {
+    // Copied from `prost-build`'s `ident.rs`, which in turn inherits this list
+    // from `syn`. Keep in sync if new keywords are added in a future Rust edition
+    match s {
+        "as" | "break" | "const" | "continue" | "crate" | "dyn" | "else" | "enum"
+        | "extern" | "false" | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop"
+        | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "self" | "Self"
+        | "static" | "struct" | "super" | "trait" | "true" | "type" | "unsafe" | "use"
+        | "where" | "while" | "async" | "await" | "abstract" | "become" | "box"
+        | "do" | "final" | "macro" | "override" | "priv" | "typeof" | "unsized" | "virtual"
+        | "yield" | "try" => format!("r#{}", s),
+        _ => s.to_string(),
+    }
+}
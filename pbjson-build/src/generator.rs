@@ -0,0 +1,352 @@
+//! Writes the generated `serde::Serialize`/`serde::Deserialize` implementations
+
+use std::io::{Result, Write};
+
+use prost_types::field_descriptor_proto::Type;
+use prost_types::EnumDescriptorProto;
+
+use crate::descriptor::TypePath;
+use crate::message::Message;
+use crate::path_map::{PathMap, PathOption};
+use crate::resolver::Resolver;
+use crate::BytesAlphabet;
+
+/// Returns true if the default (zero) value of `field` should be omitted from the JSON
+/// output given the relevant `Builder` switches, resolved for `field_path`
+#[allow(clippy::too_many_arguments)]
+fn skip_if_default(
+    field_path: &str,
+    field_type: Type,
+    is_map: bool,
+    is_repeated: bool,
+    emit_fields: &PathOption<bool>,
+    emit_enum_fields: &PathOption<bool>,
+    emit_repeated: &PathOption<bool>,
+    emit_empty_string: &PathOption<bool>,
+) -> bool {
+    if is_map || is_repeated {
+        return !emit_repeated.resolve(field_path).unwrap_or(false);
+    }
+    match field_type {
+        Type::Enum => !emit_enum_fields.resolve(field_path).unwrap_or(false),
+        Type::String | Type::Bytes => !emit_empty_string.resolve(field_path).unwrap_or(false),
+        Type::Message | Type::Group => false,
+        _ => !emit_fields.resolve(field_path).unwrap_or(false),
+    }
+}
+
+/// Returns true if `field` is a (possibly repeated) `bytes` field, and so needs base64
+/// (de)serialization rather than the generic `serde` derive path
+///
+/// Map-valued `bytes` are left to the generic path, same as before - the protobuf JSON
+/// mapping applies equally to them, but resolving a field path for a synthetic map-entry
+/// value isn't supported by the rest of the generator yet
+fn is_base64_bytes_field(field_type: Type, is_map: bool) -> bool {
+    !is_map && field_type == Type::Bytes
+}
+
+/// The `base64` `Engine` `pbjson::private::base64` (a `pub use base64;` re-export)
+/// provides for the given `alphabet`
+fn base64_engine(alphabet: BytesAlphabet) -> &'static str {
+    match alphabet {
+        BytesAlphabet::Standard => "pbjson::private::base64::engine::general_purpose::STANDARD",
+        BytesAlphabet::UrlSafe => "pbjson::private::base64::engine::general_purpose::URL_SAFE",
+    }
+}
+
+/// Generates the `serde::Serialize`/`serde::Deserialize` implementations for `message`
+#[allow(clippy::too_many_arguments)]
+pub fn generate_message<W: Write>(
+    resolver: &Resolver<'_>,
+    message: &Message,
+    writer: &mut W,
+    ignore_unknown_fields: bool,
+    btree_map_paths: &PathMap<()>,
+    emit_fields: &PathOption<bool>,
+    emit_enum_fields: &PathOption<bool>,
+    emit_repeated: &PathOption<bool>,
+    emit_empty_string: &PathOption<bool>,
+    preserve_proto_field_names: &PathOption<bool>,
+    bytes_alphabet: &PathOption<BytesAlphabet>,
+) -> Result<()> {
+    let rust_type = resolver.rust_type(message.path.path());
+    let proto_name = message.path.path().trim_start_matches('.');
+    let ignore_unknown_fields = ignore_unknown_fields || message.ignore_unknown_fields;
+
+    writeln!(writer, "impl serde::Serialize for {} {{", rust_type)?;
+    writeln!(writer, "    #[allow(deprecated)]")?;
+    writeln!(
+        writer,
+        "    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>"
+    )?;
+    writeln!(writer, "    where")?;
+    writeln!(writer, "        S: serde::Serializer,")?;
+    writeln!(writer, "    {{")?;
+    writeln!(writer, "        use serde::ser::SerializeStruct;")?;
+    writeln!(writer, "        let mut len = 0;")?;
+
+    let mut skips = Vec::new();
+    for field in message.fields.iter().filter(|field| !field.overrides.skip) {
+        let field_path = format!("{}.{}", message.path.path(), field.proto_name());
+        let is_map = field.is_map();
+        let is_repeated = !is_map && field.descriptor.label() == prost_types::field_descriptor_proto::Label::Repeated;
+        let skip = !field.overrides.emit_default
+            && skip_if_default(
+                &field_path,
+                field.descriptor.r#type(),
+                is_map,
+                is_repeated,
+                emit_fields,
+                emit_enum_fields,
+                emit_repeated,
+                emit_empty_string,
+            );
+        skips.push(skip);
+
+        if skip {
+            writeln!(
+                writer,
+                "        if self.{} != Default::default() {{ len += 1; }}",
+                field.rust_field_name
+            )?;
+        } else {
+            writeln!(writer, "        len += 1;")?;
+        }
+    }
+
+    writeln!(
+        writer,
+        "        let mut struct_ser = serializer.serialize_struct(\"{}\", len)?;",
+        proto_name
+    )?;
+
+    let serialized_fields = message.fields.iter().filter(|field| !field.overrides.skip);
+    for (field, &skip) in serialized_fields.zip(&skips) {
+        let field_path = format!("{}.{}", message.path.path(), field.proto_name());
+        if skip {
+            writeln!(
+                writer,
+                "        if self.{} != Default::default() {{",
+                field.rust_field_name
+            )?;
+        }
+        let preserve_proto_field_names = preserve_proto_field_names
+            .resolve(&field_path)
+            .unwrap_or(false);
+        let json_name = field.serialize_name(preserve_proto_field_names);
+        let is_repeated = !field.is_map()
+            && field.descriptor.label() == prost_types::field_descriptor_proto::Label::Repeated;
+
+        if is_base64_bytes_field(field.descriptor.r#type(), field.is_map()) {
+            let alphabet = bytes_alphabet.resolve(&field_path).unwrap_or_default();
+            let engine = base64_engine(alphabet);
+
+            if is_repeated {
+                writeln!(
+                    writer,
+                    "        struct_ser.serialize_field(\"{}\", &self.{}.iter().map(|v| pbjson::private::base64::Engine::encode(&{}, v)).collect::<Vec<_>>())?;",
+                    json_name, field.rust_field_name, engine
+                )?;
+            } else {
+                writeln!(
+                    writer,
+                    "        struct_ser.serialize_field(\"{}\", pbjson::private::base64::Engine::encode(&{}, &self.{}).as_str())?;",
+                    json_name, engine, field.rust_field_name
+                )?;
+            }
+        } else {
+            writeln!(
+                writer,
+                "        struct_ser.serialize_field(\"{}\", &self.{})?;",
+                json_name, field.rust_field_name
+            )?;
+        }
+        if skip {
+            writeln!(writer, "        }}")?;
+        }
+    }
+
+    // The underlying map collection type (HashMap vs BTreeMap) is chosen by
+    // `prost-build`, which both it and pbjson resolve from the same `btree_map_paths`;
+    // `serde::Serialize`/`Deserialize` are implemented identically for both, so the
+    // generated code itself doesn't need to branch on it
+    let _ = btree_map_paths;
+
+    writeln!(writer, "        struct_ser.end()")?;
+    writeln!(writer, "    }}")?;
+    writeln!(writer, "}}")?;
+
+    let mut all_accepted_names: Vec<&str> = Vec::new();
+    for field in message.fields.iter().filter(|field| !field.overrides.skip) {
+        for name in field.accepted_names() {
+            if !all_accepted_names.contains(&name) {
+                all_accepted_names.push(name);
+            }
+        }
+    }
+    let fields_const = all_accepted_names
+        .iter()
+        .map(|name| format!("\"{}\"", name))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    writeln!(
+        writer,
+        "impl<'de> serde::Deserialize<'de> for {} {{",
+        rust_type
+    )?;
+    writeln!(
+        writer,
+        "    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>"
+    )?;
+    writeln!(writer, "    where")?;
+    writeln!(writer, "        D: serde::Deserializer<'de>,")?;
+    writeln!(writer, "    {{")?;
+    writeln!(writer, "        const FIELDS: &[&str] = &[{}];", fields_const)?;
+    writeln!(writer, "        struct GeneratedVisitor;")?;
+    writeln!(writer, "        impl<'de> serde::de::Visitor<'de> for GeneratedVisitor {{")?;
+    writeln!(writer, "            type Value = {};", rust_type)?;
+    writeln!(
+        writer,
+        "            fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{"
+    )?;
+    writeln!(writer, "                formatter.write_str(\"struct {}\")", proto_name)?;
+    writeln!(writer, "            }}")?;
+    writeln!(
+        writer,
+        "            fn visit_map<V>(self, mut map_: V) -> std::result::Result<Self::Value, V::Error>"
+    )?;
+    writeln!(writer, "            where")?;
+    writeln!(writer, "                V: serde::de::MapAccess<'de>,")?;
+    writeln!(writer, "            {{")?;
+    for field in message.fields.iter().filter(|field| !field.overrides.skip) {
+        writeln!(writer, "                let mut {}__ = None;", field.rust_field_name)?;
+    }
+    writeln!(writer, "                while let Some(key) = map_.next_key::<String>()? {{")?;
+    writeln!(writer, "                    match key.as_str() {{")?;
+    for field in message.fields.iter().filter(|field| !field.overrides.skip) {
+        let pattern = field
+            .accepted_names()
+            .iter()
+            .map(|name| format!("\"{}\"", name))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        let is_repeated = !field.is_map()
+            && field.descriptor.label() == prost_types::field_descriptor_proto::Label::Repeated;
+
+        writeln!(writer, "                        {} => {{", pattern)?;
+        writeln!(
+            writer,
+            "                            if {}__.is_some() {{ return Err(serde::de::Error::duplicate_field(\"{}\")); }}",
+            field.rust_field_name,
+            field.proto_name()
+        )?;
+        if is_base64_bytes_field(field.descriptor.r#type(), field.is_map()) {
+            if is_repeated {
+                writeln!(
+                    writer,
+                    "                            {}__ = Some(map_.next_value::<Vec<pbjson::private::BytesDeserialize<_>>>()?.into_iter().map(|v| v.0).collect());",
+                    field.rust_field_name
+                )?;
+            } else {
+                writeln!(
+                    writer,
+                    "                            {}__ = Some(map_.next_value::<pbjson::private::BytesDeserialize<_>>()?.0);",
+                    field.rust_field_name
+                )?;
+            }
+        } else {
+            writeln!(
+                writer,
+                "                            {}__ = Some(map_.next_value()?);",
+                field.rust_field_name
+            )?;
+        }
+        writeln!(writer, "                        }}")?;
+    }
+    writeln!(writer, "                        _ => {{")?;
+    if ignore_unknown_fields {
+        writeln!(
+            writer,
+            "                            let _ = map_.next_value::<serde::de::IgnoredAny>()?;"
+        )?;
+    } else {
+        writeln!(
+            writer,
+            "                            return Err(serde::de::Error::unknown_field(key.as_str(), FIELDS));"
+        )?;
+    }
+    writeln!(writer, "                        }}")?;
+    writeln!(writer, "                    }}")?;
+    writeln!(writer, "                }}")?;
+    for field in message.fields.iter().filter(|field| !field.overrides.skip) {
+        writeln!(
+            writer,
+            "                let {} = {}__.unwrap_or_default();",
+            field.rust_field_name, field.rust_field_name
+        )?;
+    }
+    writeln!(writer, "                Ok({} {{", rust_type)?;
+    for field in message.fields.iter().filter(|field| !field.overrides.skip) {
+        writeln!(writer, "                    {},", field.rust_field_name)?;
+    }
+    if message.fields.iter().any(|field| field.overrides.skip) {
+        writeln!(writer, "                    ..Default::default()")?;
+    }
+    writeln!(writer, "                }})")?;
+    writeln!(writer, "            }}")?;
+    writeln!(writer, "        }}")?;
+    writeln!(
+        writer,
+        "        deserializer.deserialize_struct(\"{}\", FIELDS, GeneratedVisitor)",
+        proto_name
+    )?;
+    writeln!(writer, "    }}")?;
+    writeln!(writer, "}}")?;
+
+    Ok(())
+}
+
+/// Generates the `serde::Serialize` implementation for `descriptor`; no
+/// `serde::Deserialize` implementation is generated for enums
+pub fn generate_enum<W: Write>(
+    resolver: &Resolver<'_>,
+    type_path: &TypePath,
+    descriptor: &EnumDescriptorProto,
+    writer: &mut W,
+    use_integers_for_enums: &PathOption<bool>,
+) -> Result<()> {
+    let rust_type = resolver.rust_type(type_path.path());
+    let use_integers_for_enums = use_integers_for_enums
+        .resolve(type_path.path())
+        .unwrap_or(false);
+
+    writeln!(writer, "impl serde::Serialize for {} {{", rust_type)?;
+    writeln!(
+        writer,
+        "    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>"
+    )?;
+    writeln!(writer, "    where")?;
+    writeln!(writer, "        S: serde::Serializer,")?;
+    writeln!(writer, "    {{")?;
+    if use_integers_for_enums {
+        writeln!(writer, "        serializer.serialize_i32(*self as i32)")?;
+    } else {
+        writeln!(writer, "        let variant = match self {{")?;
+        for value in &descriptor.value {
+            let variant = resolver.rust_enum_variant(descriptor.name(), value.name());
+            let json = resolver.json_enum_variant(descriptor.name(), value.name());
+            writeln!(
+                writer,
+                "            Self::{} => \"{}\",",
+                variant, json
+            )?;
+        }
+        writeln!(writer, "        }};")?;
+        writeln!(writer, "        serializer.serialize_str(variant)")?;
+    }
+    writeln!(writer, "    }}")?;
+    writeln!(writer, "}}")?;
+
+    Ok(())
+}
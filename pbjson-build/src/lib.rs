@@ -65,6 +65,34 @@
 //! The module will now contain the generated prost structs for your protobuf definition
 //! along with compliant implementations of [serde::Serialize][2] and [serde::Deserialize][3]
 //!
+//! # protoc plugin
+//!
+//! For build graphs that invoke `protoc` directly, or tools such as `buf` or Bazel's
+//! `rules_proto` that drive protoc plugins without going through `prost-build`, the
+//! `protoc-gen-pbjson` binary shipped alongside this crate speaks the standard protoc
+//! compiler-plugin protocol. Install it with `cargo install pbjson-build --bin
+//! protoc-gen-pbjson` and invoke it as `protoc --pbjson_out=<opts>:<dir>`, where `<opts>`
+//! is a comma-separated list of the same switches as the `Builder` above, e.g.
+//! `emit_fields,btree_map=.mypackage.MyMessage`.
+//!
+//! # In-proto customization
+//!
+//! Some JSON-shaping decisions are easier to keep next to the schema than to thread
+//! through every downstream crate's `build.rs`. Annotate a field with `(pbjson.skip)`,
+//! `(pbjson.rename)` or `(pbjson.emit_default)`, or a message with
+//! `(pbjson.ignore_unknown_fields)`, and `pbjson-build` will honour them - this does
+//! require `protoc` to have the `proto/pbjson.proto` extension definitions shipped with
+//! this crate on its include path, since that's what lets `protoc` parse the
+//! `(pbjson.*)` syntax in the first place.
+//!
+//! `pbjson-build` itself reads the configured value back out of the raw bytes of the
+//! registered descriptor, by the extension's wire field number, rather than relying on
+//! `protoc` resolving it to a named option: once `protoc` has resolved an extension, it
+//! clears it from the descriptor's uninterpreted-option data, leaving nothing there for
+//! a later reader to find. This also means in-proto options are only visible when a
+//! descriptor is registered from its encoded bytes - [`Builder::register_descriptors`]
+//! or [`Builder::register_encoded_file_descriptor`], not [`Builder::register_file_descriptor`].
+//!
 //! [1]: https://docs.rs/prost-build
 //! [2]: https://docs.rs/serde/1.0.130/serde/trait.Serialize.html
 //! [3]: https://docs.rs/serde/1.0.130/serde/trait.Deserialize.html
@@ -86,6 +114,7 @@ use std::path::PathBuf;
 
 use crate::descriptor::{Descriptor, Package};
 use crate::message::resolve_message;
+use crate::path_map::{PathMap, PathOption};
 use crate::{
     generator::{generate_enum, generate_message},
     resolver::Resolver,
@@ -95,23 +124,42 @@ mod descriptor;
 mod escape;
 mod generator;
 mod message;
+mod options;
+mod path_map;
 mod resolver;
+mod wire;
+
+/// The base64 alphabet used when encoding a `bytes` field to JSON
+///
+/// The protobuf JSON mapping requires producers to emit standard, padded base64, while
+/// decoders must accept both alphabets, with or without padding - the generated
+/// deserialize code always does the latter regardless of this setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BytesAlphabet {
+    /// `A-Z`, `a-z`, `0-9`, `+`, `/`, padded with `=` - the protobuf JSON default
+    #[default]
+    Standard,
+    /// `A-Z`, `a-z`, `0-9`, `-`, `_`, padded with `=` - common among producers that
+    /// embed the encoded value in a URL or filename
+    UrlSafe,
+}
 
 #[derive(Debug, Default)]
 pub struct Builder {
     descriptors: descriptor::DescriptorSet,
-    exclude: Vec<String>,
+    exclude: PathMap<()>,
     out_dir: Option<PathBuf>,
     extern_paths: Vec<(String, String)>,
     retain_enum_prefix: bool,
     ignore_unknown_fields: bool,
-    btree_map_paths: Vec<String>,
-    emit_fields: bool,
-    emit_enum_fields: bool,
-    emit_repeated: bool,
-    emit_empty_string: bool,
-    use_integers_for_enums: bool,
-    preserve_proto_field_names: bool,
+    btree_map_paths: PathMap<()>,
+    emit_fields: PathOption<bool>,
+    emit_enum_fields: PathOption<bool>,
+    emit_repeated: PathOption<bool>,
+    emit_empty_string: PathOption<bool>,
+    use_integers_for_enums: PathOption<bool>,
+    preserve_proto_field_names: PathOption<bool>,
+    bytes_alphabet: PathOption<BytesAlphabet>,
     strip_enum_vairant_prefix_and_to_lowercase: bool,
     enum_prefixes_to_keep: HashSet<String>,
 }
@@ -141,17 +189,34 @@ impl Builder {
     }
 
     /// Register a decoded `FileDescriptor` with this `Builder`
+    ///
+    /// Note that, unlike [`Self::register_descriptors`]/[`Self::register_encoded_file_descriptor`],
+    /// any `(pbjson.*)` in-proto option set on `file` won't be honoured: `prost_types`
+    /// has already discarded the resolved extension field(s) by the time `file` is a
+    /// decoded [`FileDescriptorProto`], leaving no raw bytes behind to read them back
+    /// from
     pub fn register_file_descriptor(&mut self, file: FileDescriptorProto) -> &mut Self {
         self.descriptors.register_file_descriptor(file);
         self
     }
 
+    /// Register a single encoded `FileDescriptorProto` with this `Builder`
+    ///
+    /// Like [`Self::register_descriptors`], this honours any `(pbjson.*)` in-proto
+    /// option set on the file
+    pub fn register_encoded_file_descriptor(&mut self, file: &[u8]) -> Result<&mut Self> {
+        self.descriptors.register_encoded_file_descriptor(file)?;
+        Ok(self)
+    }
+
     /// Don't generate code for the following type prefixes
+    ///
+    /// Paths use the same matching rules as [`Self::btree_map`]
     pub fn exclude<S: Into<String>, I: IntoIterator<Item = S>>(
         &mut self,
         prefixes: I,
     ) -> &mut Self {
-        self.exclude.extend(prefixes.into_iter().map(Into::into));
+        self.exclude.insert_all(prefixes, ());
         self
     }
 
@@ -181,46 +246,126 @@ impl Builder {
     }
 
     /// Generate Rust BTreeMap implementations for Protobuf map type fields.
+    ///
+    /// Paths are matched using the same rules as `prost-build`'s `btree_map`: a
+    /// leading-dot path (e.g. `.mypackage.MyMessage`) is a fully-qualified match on that
+    /// type or anything nested beneath it, a dotless path (e.g. `MyMessage.my_field`) is
+    /// suffix-matched against the fully-qualified field name. When several paths match,
+    /// one naming the field exactly wins, then a dotless match over a leading-dot one,
+    /// then the longest.
     pub fn btree_map<S: Into<String>, I: IntoIterator<Item = S>>(&mut self, paths: I) -> &mut Self {
-        self.btree_map_paths
-            .extend(paths.into_iter().map(Into::into));
+        self.btree_map_paths.insert_all(paths, ());
         self
     }
 
     /// Output fields with their default values.
     pub fn emit_fields(&mut self) -> &mut Self {
-        self.emit_fields = true;
+        self.emit_fields.set_default(true);
+        self
+    }
+
+    /// Like [`Self::emit_fields`], but only for the given message/field paths, leaving
+    /// the behaviour for everything else unchanged
+    pub fn emit_fields_for<S: Into<String>, I: IntoIterator<Item = S>>(
+        &mut self,
+        paths: I,
+    ) -> &mut Self {
+        self.emit_fields.set_for(paths, true);
         self
     }
 
     /// Output enum fields with their default values.
     pub fn emit_enum_fields(&mut self) -> &mut Self {
-        self.emit_enum_fields = true;
+        self.emit_enum_fields.set_default(true);
+        self
+    }
+
+    /// Like [`Self::emit_enum_fields`], but only for the given message/field paths
+    pub fn emit_enum_fields_for<S: Into<String>, I: IntoIterator<Item = S>>(
+        &mut self,
+        paths: I,
+    ) -> &mut Self {
+        self.emit_enum_fields.set_for(paths, true);
         self
     }
 
     // Output repeated fields if empty.
     pub fn emit_repeated(&mut self) -> &mut Self {
-        self.emit_repeated = true;
+        self.emit_repeated.set_default(true);
+        self
+    }
+
+    /// Like [`Self::emit_repeated`], but only for the given message/field paths
+    pub fn emit_repeated_for<S: Into<String>, I: IntoIterator<Item = S>>(
+        &mut self,
+        paths: I,
+    ) -> &mut Self {
+        self.emit_repeated.set_for(paths, true);
         self
     }
 
     // Output empty strings if empty.
     pub fn emit_empty_string(&mut self) -> &mut Self {
-        self.emit_empty_string = true;
+        self.emit_empty_string.set_default(true);
+        self
+    }
+
+    /// Like [`Self::emit_empty_string`], but only for the given message/field paths
+    pub fn emit_empty_string_for<S: Into<String>, I: IntoIterator<Item = S>>(
+        &mut self,
+        paths: I,
+    ) -> &mut Self {
+        self.emit_empty_string.set_for(paths, true);
         self
     }
 
     // print integers instead of enum names.
     pub fn use_integers_for_enums(&mut self) -> &mut Self {
-        self.use_integers_for_enums = true;
+        self.use_integers_for_enums.set_default(true);
+        self
+    }
+
+    /// Like [`Self::use_integers_for_enums`], but only for the given enum paths
+    pub fn use_integers_for_enums_for<S: Into<String>, I: IntoIterator<Item = S>>(
+        &mut self,
+        paths: I,
+    ) -> &mut Self {
+        self.use_integers_for_enums.set_for(paths, true);
         self
     }
 
     /// Output fields with their original names as defined in their proto schemas, instead of
     /// lowerCamelCase
     pub fn preserve_proto_field_names(&mut self) -> &mut Self {
-        self.preserve_proto_field_names = true;
+        self.preserve_proto_field_names.set_default(true);
+        self
+    }
+
+    /// Like [`Self::preserve_proto_field_names`], but only for the given message/field
+    /// paths
+    pub fn preserve_proto_field_names_for<S: Into<String>, I: IntoIterator<Item = S>>(
+        &mut self,
+        paths: I,
+    ) -> &mut Self {
+        self.preserve_proto_field_names.set_for(paths, true);
+        self
+    }
+
+    /// Encode `bytes` fields using the URL-safe base64 alphabet instead of the spec's
+    /// default standard alphabet. Decoding always accepts either alphabet, so this only
+    /// affects generated output
+    pub fn base64_url_safe(&mut self) -> &mut Self {
+        self.bytes_alphabet.set_default(BytesAlphabet::UrlSafe);
+        self
+    }
+
+    /// Like [`Self::base64_url_safe`], but only for the given message/field paths
+    pub fn base64_url_safe_for<S: Into<String>, I: IntoIterator<Item = S>>(
+        &mut self,
+        paths: I,
+    ) -> &mut Self {
+        self.bytes_alphabet
+            .set_for(paths, BytesAlphabet::UrlSafe);
         self
     }
 
@@ -239,6 +384,102 @@ impl Builder {
         self
     }
 
+    /// Applies the comma-separated `key=value`/flag tokens found in a protoc plugin
+    /// `parameter` string to the matching `Builder` switches
+    ///
+    /// This lets [`protoc-gen-pbjson`](index.html#protoc-plugin) accept the same options
+    /// as the `build.rs` API, e.g. `emit_fields,btree_map=.mypackage.MyMessage`, including
+    /// the path-scoped `*_for` variants, e.g.
+    /// `emit_fields_for=.mypackage.MyMessage:.mypackage.OtherMessage`
+    pub fn parse_parameter(&mut self, parameter: &str) -> Result<&mut Self> {
+        for token in parameter.split(',').filter(|s| !s.is_empty()) {
+            match token.split_once('=') {
+                Some(("btree_map", paths)) => {
+                    self.btree_map(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some(("extern_path", mapping)) => {
+                    let (proto_path, rust_path) = mapping.split_once('=').ok_or_else(|| {
+                        Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("invalid extern_path parameter: {}", mapping),
+                        )
+                    })?;
+                    self.extern_path(proto_path, rust_path);
+                }
+                Some(("exclude", paths)) => {
+                    self.exclude(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some(("emit_fields_for", paths)) => {
+                    self.emit_fields_for(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some(("emit_enum_fields_for", paths)) => {
+                    self.emit_enum_fields_for(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some(("emit_repeated_for", paths)) => {
+                    self.emit_repeated_for(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some(("emit_empty_string_for", paths)) => {
+                    self.emit_empty_string_for(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some(("use_integers_for_enums_for", paths)) => {
+                    self.use_integers_for_enums_for(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some(("preserve_proto_field_names_for", paths)) => {
+                    self.preserve_proto_field_names_for(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some(("base64_url_safe_for", paths)) => {
+                    self.base64_url_safe_for(paths.split(':').filter(|s| !s.is_empty()));
+                }
+                Some((key, _)) => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("unknown pbjson-build parameter: {}", key),
+                    ))
+                }
+                None => match token {
+                    "emit_fields" => {
+                        self.emit_fields();
+                    }
+                    "emit_enum_fields" => {
+                        self.emit_enum_fields();
+                    }
+                    "emit_repeated" => {
+                        self.emit_repeated();
+                    }
+                    "emit_empty_string" => {
+                        self.emit_empty_string();
+                    }
+                    "use_integers_for_enums" => {
+                        self.use_integers_for_enums();
+                    }
+                    "preserve_proto_field_names" => {
+                        self.preserve_proto_field_names();
+                    }
+                    "base64_url_safe" => {
+                        self.base64_url_safe();
+                    }
+                    "retain_enum_prefix" => {
+                        self.retain_enum_prefix();
+                    }
+                    "ignore_unknown_fields" => {
+                        self.ignore_unknown_fields();
+                    }
+                    "strip_enum_vairant_prefix_and_to_lowercase" => {
+                        self.strip_enum_vairant_prefix_and_to_lowercase();
+                    }
+                    flag => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("unknown pbjson-build flag: {}", flag),
+                        ))
+                    }
+                },
+            }
+        }
+
+        Ok(self)
+    }
+
     /// Generates code for all registered types where `prefixes` contains a prefix of
     /// the fully-qualified path of the type
     pub fn build<S: AsRef<str>>(&mut self, prefixes: &[S]) -> Result<()> {
@@ -282,13 +523,10 @@ impl Builder {
         mut write_factory: F,
     ) -> Result<Vec<(Package, W)>> {
         let iter = self.descriptors.iter().filter(move |(t, _)| {
-            let exclude = self
-                .exclude
-                .iter()
-                .any(|prefix| t.prefix_match(prefix.as_ref()).is_some());
+            let exclude = self.exclude.matches(t.path());
             let include = prefixes
                 .iter()
-                .any(|prefix| t.prefix_match(prefix.as_ref()).is_some());
+                .any(|prefix| crate::path_map::path_matches(prefix.as_ref(), t.path()));
             include && !exclude
         });
 
@@ -318,21 +556,24 @@ impl Builder {
                     type_path,
                     descriptor,
                     writer,
-                    self.use_integers_for_enums,
+                    &self.use_integers_for_enums,
                 )?,
                 Descriptor::Message(descriptor) => {
-                    if let Some(message) = resolve_message(&self.descriptors, descriptor) {
+                    if let Some(message) =
+                        resolve_message(type_path, descriptor, self.descriptors.overrides())
+                    {
                         generate_message(
                             &resolver,
                             &message,
                             writer,
                             self.ignore_unknown_fields,
                             &self.btree_map_paths,
-                            self.emit_fields,
-                            self.emit_enum_fields,
-                            self.emit_repeated,
-                            self.emit_empty_string,
-                            self.preserve_proto_field_names,
+                            &self.emit_fields,
+                            &self.emit_enum_fields,
+                            &self.emit_repeated,
+                            &self.emit_empty_string,
+                            &self.preserve_proto_field_names,
+                            &self.bytes_alphabet,
                         )?
                     }
                 }
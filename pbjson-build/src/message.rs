@@ -0,0 +1,192 @@
+//! Resolves a [`DescriptorProto`] into the richer [`Message`]/[`Field`] representation
+//! consumed by [`crate::generator`]
+
+use prost_types::field_descriptor_proto::Type;
+use prost_types::{DescriptorProto, FieldDescriptorProto};
+
+use crate::descriptor::TypePath;
+use crate::escape::escape_ident;
+use crate::options::{FieldOverrides, RawOverrides};
+
+/// A field belonging to a [`Message`], together with the names it will be known by
+/// on the Rust and JSON sides
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub descriptor: FieldDescriptorProto,
+    /// The name of the generated struct field, as produced by `prost-build`
+    pub rust_field_name: String,
+    /// The default (lowerCamelCase) JSON name for this field
+    pub json_name: String,
+    /// The field's explicit `json_name`, if the `.proto` source set one that differs
+    /// from [`Self::json_name`]
+    pub explicit_json_name: Option<String>,
+    /// The value field of the synthetic map-entry message, if this field is a protobuf map
+    pub map_value: Option<FieldDescriptorProto>,
+    /// This field's `(pbjson.skip)`/`(pbjson.rename)`/`(pbjson.emit_default)` overrides,
+    /// if any were set in the `.proto` source
+    pub overrides: FieldOverrides,
+}
+
+impl Field {
+    /// The field's declared name, as it appears in the `.proto` source
+    pub fn proto_name(&self) -> &str {
+        self.descriptor.name()
+    }
+
+    pub fn is_map(&self) -> bool {
+        self.map_value.is_some()
+    }
+
+    /// The name this field should be serialized under: a `(pbjson.rename)` from the
+    /// `.proto` source wins outright, as it is the schema author's explicit say over
+    /// the wire format; failing that, honour an explicit `json_name` over the derived
+    /// lowerCamelCase name, with `preserve_proto_field_names` taking precedence over both
+    /// as it is an explicit opt-out of the spec's default JSON naming on the `Builder`
+    /// itself
+    pub fn serialize_name(&self, preserve_proto_field_names: bool) -> &str {
+        if let Some(rename) = self.overrides.rename.as_deref() {
+            return rename;
+        }
+        if preserve_proto_field_names {
+            return self.proto_name();
+        }
+        self.explicit_json_name.as_deref().unwrap_or(&self.json_name)
+    }
+
+    /// All the names a conformant deserializer must accept for this field: its proto
+    /// name, its derived lowerCamelCase name, and - if set - its explicit `json_name`
+    /// and `(pbjson.rename)`, deduplicated
+    pub fn accepted_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::with_capacity(4);
+        for name in [
+            self.proto_name(),
+            self.json_name.as_str(),
+            self.explicit_json_name.as_deref().unwrap_or(""),
+            self.overrides.rename.as_deref().unwrap_or(""),
+        ] {
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+}
+
+/// A resolved message, ready for code generation
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub path: TypePath,
+    pub fields: Vec<Field>,
+    /// Whether this message's `.proto` source set `option (pbjson.ignore_unknown_fields)`
+    pub ignore_unknown_fields: bool,
+}
+
+/// Resolves `descriptor` into a [`Message`], or returns `None` if it is a synthetic
+/// map-entry message and should be skipped
+pub fn resolve_message(
+    path: &TypePath,
+    descriptor: &DescriptorProto,
+    overrides: &RawOverrides,
+) -> Option<Message> {
+    if descriptor
+        .options
+        .as_ref()
+        .map(|o| o.map_entry())
+        .unwrap_or(false)
+    {
+        return None;
+    }
+
+    let fields = descriptor
+        .field
+        .iter()
+        .map(|field| resolve_field(path, descriptor, field, overrides))
+        .collect();
+
+    Some(Message {
+        path: path.clone(),
+        fields,
+        ignore_unknown_fields: overrides.message(path.path()).ignore_unknown_fields,
+    })
+}
+
+fn resolve_field(
+    path: &TypePath,
+    message: &DescriptorProto,
+    field: &FieldDescriptorProto,
+    overrides: &RawOverrides,
+) -> Field {
+    let rust_field_name = escape_ident(&to_snake_case(field.name()));
+    let json_name = to_lower_camel_case(field.name());
+
+    let explicit_json_name = field
+        .json_name
+        .as_deref()
+        .filter(|explicit| *explicit != json_name)
+        .map(str::to_string);
+
+    let map_value = (field.r#type() == Type::Message)
+        .then(|| resolve_map_value(message, field.type_name()))
+        .flatten()
+        .cloned();
+
+    let field_path = format!("{}.{}", path.path(), field.name());
+
+    Field {
+        descriptor: field.clone(),
+        rust_field_name,
+        json_name,
+        explicit_json_name,
+        map_value,
+        overrides: overrides.field(&field_path),
+    }
+}
+
+fn resolve_map_value<'a>(
+    message: &'a DescriptorProto,
+    type_name: &str,
+) -> Option<&'a FieldDescriptorProto> {
+    let simple_name = type_name.rsplit('.').next()?;
+    let nested = message.nested_type.iter().find(|nested| {
+        nested.name() == simple_name
+            && nested
+                .options
+                .as_ref()
+                .map(|o| o.map_entry())
+                .unwrap_or(false)
+    })?;
+    nested.field.iter().find(|f| f.name() == "value")
+}
+
+fn to_snake_case(s: &str) -> String {
+    // Protobuf field names are already lower_snake_case by convention, but guard against
+    // the rare schema that isn't
+    let mut out = String::with_capacity(s.len());
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            out.push('_');
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// Converts a proto field name to the default lowerCamelCase JSON name, per the
+/// [protobuf JSON mapping][1]
+///
+/// [1]: https://developers.google.com/protocol-buffers/docs/proto3#json
+pub fn to_lower_camel_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut capitalize = false;
+    for c in s.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if capitalize {
+            out.extend(c.to_uppercase());
+            capitalize = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,272 @@
+//! Reads pbjson's own field/message options - declared as custom extensions in
+//! `proto/pbjson.proto` - directly off the raw wire bytes of a registered
+//! `FileDescriptorSet`/`FileDescriptorProto`
+//!
+//! By the time a `.proto` file reaches `protoc`, the `(pbjson.skip) = true` syntax this
+//! feature relies on already requires `pbjson.proto` to be on `protoc`'s include path
+//! just to parse - but once parsed, `protoc` resolves the extension and serializes its
+//! value at the extension's wire field number, same as any other field. It does *not*
+//! leave anything behind in `UninterpretedOption`, which is cleared as soon as an
+//! option is resolved - so by the time `pbjson-build` sees the compiled descriptors,
+//! that field is always empty for a real `protoc` run. The only way to recover the
+//! configured value is to read the raw field back out by its wire number, which also
+//! means these overrides are only visible when a descriptor was registered from its
+//! encoded bytes (see [`crate::descriptor::DescriptorSet::register_encoded`]) - a
+//! `prost_types::FieldOptions`/`MessageOptions` that has already been decoded has had
+//! any field number it doesn't know about discarded by `prost`, with nothing left to
+//! read back
+
+use std::collections::HashMap;
+
+use crate::wire;
+
+/// The field number `pbjson.proto` assigns to each extension, see `proto/pbjson.proto`
+mod ext {
+    pub(super) const FIELD_SKIP: u32 = 50_001;
+    pub(super) const FIELD_RENAME: u32 = 50_002;
+    pub(super) const FIELD_EMIT_DEFAULT: u32 = 50_003;
+    pub(super) const MESSAGE_IGNORE_UNKNOWN_FIELDS: u32 = 50_001;
+}
+
+/// The field numbers of the descriptor.proto messages this module walks - see
+/// `google/protobuf/descriptor.proto`
+mod tag {
+    pub(super) const FILE_DESCRIPTOR_SET_FILE: u32 = 1;
+    pub(super) const FILE_PACKAGE: u32 = 2;
+    pub(super) const FILE_MESSAGE_TYPE: u32 = 4;
+    pub(super) const MESSAGE_NAME: u32 = 1;
+    pub(super) const MESSAGE_FIELD: u32 = 2;
+    pub(super) const MESSAGE_NESTED_TYPE: u32 = 3;
+    pub(super) const MESSAGE_OPTIONS: u32 = 7;
+    pub(super) const FIELD_NAME: u32 = 1;
+    pub(super) const FIELD_OPTIONS: u32 = 8;
+}
+
+/// Per-field generation overrides read from the `.proto` source, e.g.
+/// `string name = 1 [(pbjson.skip) = true];`
+#[derive(Debug, Clone, Default)]
+pub struct FieldOverrides {
+    /// Omit this field from both the `Serialize` impl and the deserialize visitor
+    pub skip: bool,
+    /// Serialize/accept this field under the given JSON key instead of its derived name
+    pub rename: Option<String>,
+    /// Always emit this field, even at its zero value, regardless of the `Builder`'s
+    /// `emit_*` switches
+    pub emit_default: bool,
+}
+
+/// Per-message generation overrides read from the `.proto` source, e.g.
+/// `message Foo { option (pbjson.ignore_unknown_fields) = true; }`
+#[derive(Debug, Clone, Default)]
+pub struct MessageOverrides {
+    /// Don't error out on unknown fields when deserializing this message, regardless of
+    /// the `Builder`'s crate-wide [`ignore_unknown_fields`](crate::Builder::ignore_unknown_fields)
+    pub ignore_unknown_fields: bool,
+}
+
+/// The [`FieldOverrides`]/[`MessageOverrides`] recovered from the raw bytes of every
+/// `FileDescriptorProto` registered with a [`crate::descriptor::DescriptorSet`], keyed
+/// by the fully-qualified path of the field/message they were read from
+#[derive(Debug, Default)]
+pub struct RawOverrides {
+    fields: HashMap<String, FieldOverrides>,
+    messages: HashMap<String, MessageOverrides>,
+}
+
+impl RawOverrides {
+    /// Reads the overrides out of the raw bytes of an encoded `FileDescriptorSet`,
+    /// merging them into this `RawOverrides`
+    pub fn collect_from_file_descriptor_set(&mut self, descriptors: &[u8]) {
+        for file in wire::length_delimited_fields(descriptors, tag::FILE_DESCRIPTOR_SET_FILE) {
+            self.collect_from_file_descriptor(file);
+        }
+    }
+
+    /// Reads the overrides out of the raw bytes of a single encoded `FileDescriptorProto`,
+    /// merging them into this `RawOverrides`
+    pub fn collect_from_file_descriptor(&mut self, file: &[u8]) {
+        // Mirror `descriptor::Package`'s `Display` impl exactly (no leading dot, an
+        // empty package formats as ""), since that's the convention `TypePath::path`
+        // uses for every path `message.rs` looks up in this `RawOverrides` with
+        let package = wire::string_field(file, tag::FILE_PACKAGE).unwrap_or_default();
+
+        for message in wire::length_delimited_fields(file, tag::FILE_MESSAGE_TYPE) {
+            self.collect_from_message(message, package);
+        }
+    }
+
+    fn collect_from_message(&mut self, message: &[u8], parent_path: &str) {
+        let name = wire::string_field(message, tag::MESSAGE_NAME).unwrap_or_default();
+        let path = format!("{}.{}", parent_path, name);
+
+        if let Some(options) = wire::length_delimited_fields(message, tag::MESSAGE_OPTIONS).last()
+        {
+            self.messages.insert(path.clone(), message_overrides_from_raw(options));
+        }
+
+        for field in wire::length_delimited_fields(message, tag::MESSAGE_FIELD) {
+            let field_name = wire::string_field(field, tag::FIELD_NAME).unwrap_or_default();
+            let field_path = format!("{}.{}", path, field_name);
+
+            if let Some(options) = wire::length_delimited_fields(field, tag::FIELD_OPTIONS).last() {
+                self.fields.insert(field_path, field_overrides_from_raw(options));
+            }
+        }
+
+        for nested in wire::length_delimited_fields(message, tag::MESSAGE_NESTED_TYPE) {
+            self.collect_from_message(nested, &path);
+        }
+    }
+
+    /// The [`FieldOverrides`] configured for the field at `fq_field_path`, if any
+    pub fn field(&self, fq_field_path: &str) -> FieldOverrides {
+        self.fields.get(fq_field_path).cloned().unwrap_or_default()
+    }
+
+    /// The [`MessageOverrides`] configured for the message at `fq_message_path`, if any
+    pub fn message(&self, fq_message_path: &str) -> MessageOverrides {
+        self.messages
+            .get(fq_message_path)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Resolves the [`FieldOverrides`] from the raw bytes of a `FieldOptions` submessage
+fn field_overrides_from_raw(options: &[u8]) -> FieldOverrides {
+    FieldOverrides {
+        skip: wire::bool_field(options, ext::FIELD_SKIP).unwrap_or(false),
+        rename: wire::string_field(options, ext::FIELD_RENAME).map(str::to_string),
+        emit_default: wire::bool_field(options, ext::FIELD_EMIT_DEFAULT).unwrap_or(false),
+    }
+}
+
+/// Resolves the [`MessageOverrides`] from the raw bytes of a `MessageOptions` submessage
+fn message_overrides_from_raw(options: &[u8]) -> MessageOverrides {
+    MessageOverrides {
+        ignore_unknown_fields: wire::bool_field(options, ext::MESSAGE_IGNORE_UNKNOWN_FIELDS)
+            .unwrap_or(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::Builder;
+
+    use super::{ext, tag};
+
+    fn varint(mut v: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let byte = (v & 0x7f) as u8;
+            v >>= 7;
+            if v == 0 {
+                out.push(byte);
+                break;
+            }
+            out.push(byte | 0x80);
+        }
+        out
+    }
+
+    fn tag_key(field_number: u32, wire_type: u8) -> Vec<u8> {
+        varint(((field_number as u64) << 3) | wire_type as u64)
+    }
+
+    fn len_delim(field_number: u32, payload: &[u8]) -> Vec<u8> {
+        let mut out = tag_key(field_number, 2);
+        out.extend(varint(payload.len() as u64));
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn string_field(field_number: u32, s: &str) -> Vec<u8> {
+        len_delim(field_number, s.as_bytes())
+    }
+
+    fn varint_field(field_number: u32, v: u64) -> Vec<u8> {
+        let mut out = tag_key(field_number, 0);
+        out.extend(varint(v));
+        out
+    }
+
+    /// Hand-encodes a tiny `FileDescriptorSet` with one message carrying each of the
+    /// `(pbjson.*)` in-proto options this module reads - the only way to exercise them,
+    /// since `prost_types` has no way to construct a `FieldOptions`/`MessageOptions`
+    /// with an unresolved custom extension set, only a real `protoc` run produces that
+    fn encode_test_descriptor_set() -> Vec<u8> {
+        // FieldDescriptorProto "skip_me": a string field with (pbjson.skip) = true
+        let skip_options = varint_field(ext::FIELD_SKIP, 1);
+        let skip_field = [
+            string_field(tag::FIELD_NAME, "skip_me"),
+            varint_field(4, 1), // label = LABEL_OPTIONAL
+            varint_field(5, 9), // type = TYPE_STRING
+            len_delim(tag::FIELD_OPTIONS, &skip_options),
+        ]
+        .concat();
+
+        // FieldDescriptorProto "plain": a string field with (pbjson.rename) = "custom_name"
+        let rename_options = string_field(ext::FIELD_RENAME, "custom_name");
+        let rename_field = [
+            string_field(tag::FIELD_NAME, "plain"),
+            varint_field(4, 1),
+            varint_field(5, 9),
+            len_delim(tag::FIELD_OPTIONS, &rename_options),
+        ]
+        .concat();
+
+        // MessageOptions with (pbjson.ignore_unknown_fields) = true
+        let message_options = varint_field(ext::MESSAGE_IGNORE_UNKNOWN_FIELDS, 1);
+
+        // DescriptorProto "Msg"
+        let message = [
+            string_field(tag::MESSAGE_NAME, "Msg"),
+            len_delim(tag::MESSAGE_FIELD, &skip_field),
+            len_delim(tag::MESSAGE_FIELD, &rename_field),
+            len_delim(tag::MESSAGE_OPTIONS, &message_options),
+        ]
+        .concat();
+
+        // FileDescriptorProto
+        let file = [
+            string_field(tag::FILE_PACKAGE, "pkgtest"),
+            len_delim(tag::FILE_MESSAGE_TYPE, &message),
+        ]
+        .concat();
+
+        // FileDescriptorSet
+        len_delim(tag::FILE_DESCRIPTOR_SET_FILE, &file)
+    }
+
+    #[test]
+    fn in_proto_overrides_are_honoured_end_to_end() {
+        let descriptor_set = encode_test_descriptor_set();
+
+        let mut builder = Builder::new();
+        builder.register_descriptors(&descriptor_set).unwrap();
+
+        let outputs = builder
+            .generate(&[".pkgtest"], |_package| {
+                Ok::<_, std::io::Error>(Cursor::new(Vec::<u8>::new()))
+            })
+            .unwrap();
+
+        let generated: String = outputs
+            .into_iter()
+            .map(|(_, cursor)| String::from_utf8(cursor.into_inner()).unwrap())
+            .collect();
+
+        // (pbjson.skip) removes the field from both the Serialize and Deserialize impls
+        assert!(!generated.contains("skip_me"));
+
+        // (pbjson.rename) is used as the serialized JSON key instead of the derived
+        // "plain" - deserialization still accepts the proto name too, by design
+        assert!(generated.contains("\"custom_name\""));
+
+        // (pbjson.ignore_unknown_fields) swaps the unknown-field error for a silent skip
+        assert!(generated.contains("IgnoredAny"));
+        assert!(!generated.contains("unknown_field(key.as_str(), FIELDS)"));
+    }
+}
@@ -0,0 +1,189 @@
+//! A path-based matcher compatible with `prost-build`'s `path::PathMap`, used to resolve
+//! per-path `Builder` options such as `btree_map`, `exclude` and field casing
+
+/// Maps fully-qualified protobuf paths (packages, messages or fields) to a configured
+/// value of type `T`, using the same matching rules as `prost-build`'s internal
+/// `path::PathMap`:
+///
+/// - A leading-dot path (e.g. `.mypackage.MyMessage`) is matched as a fully-qualified,
+///   absolute path
+/// - A dotless path (e.g. `MyMessage.my_field`) is suffix-matched against the
+///   fully-qualified name, so it matches regardless of which package it appears in
+/// - When several configured paths match, a path that matches `fq_path` exactly (in
+///   full, not just a suffix or an ancestor of it) always wins first; failing that, a
+///   dotless (suffix) match wins over a leading-dot (absolute, ancestor-only) match,
+///   regardless of either path's length; ties within the same tier are broken by the
+///   longest configured path. This mirrors `prost-build`'s own tie-break order
+#[derive(Debug, Clone, Default)]
+pub struct PathMap<T> {
+    entries: Vec<(String, T)>,
+}
+
+impl<T> PathMap<T> {
+    /// Associates `value` with every path in `paths`
+    pub fn insert_all<S, I>(&mut self, paths: I, value: T)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = S>,
+        T: Clone,
+    {
+        for path in paths {
+            self.entries.push((path.into(), value.clone()));
+        }
+    }
+
+    /// Returns the value of the most specific configured path that matches
+    /// `fq_path` (a fully-qualified path; its leading dot is optional), if any
+    pub fn get(&self, fq_path: &str) -> Option<&T> {
+        self.entries
+            .iter()
+            .filter(|(path, _)| path_matches(path, fq_path))
+            .max_by_key(|(path, _)| (is_exact_path(path, fq_path), is_suffix_path(path), path.len()))
+            .map(|(_, value)| value)
+    }
+
+    /// Returns true if any configured path matches `fq_path`
+    pub fn matches(&self, fq_path: &str) -> bool {
+        self.get(fq_path).is_some()
+    }
+}
+
+/// A [`PathMap`]-backed switch that can be turned on globally, or only for a set of
+/// paths, used to make options like `emit_fields` or `preserve_proto_field_names`
+/// path-scoped instead of crate-global
+///
+/// The most specific matching path wins, falling back to the global value - set via
+/// [`Self::set_default`] - when nothing more specific was configured for a given path
+#[derive(Debug, Clone, Default)]
+pub struct PathOption<T> {
+    default: Option<T>,
+    overrides: PathMap<T>,
+}
+
+impl<T: Clone> PathOption<T> {
+    /// Sets the value returned for paths with no more specific override
+    pub fn set_default(&mut self, value: T) {
+        self.default = Some(value);
+    }
+
+    /// Overrides the value for every path in `paths`
+    pub fn set_for<S, I>(&mut self, paths: I, value: T)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = S>,
+    {
+        self.overrides.insert_all(paths, value);
+    }
+
+    /// Resolves the effective value for `fq_path`, if either a path-specific override or
+    /// a global default has been configured
+    pub fn resolve(&self, fq_path: &str) -> Option<T> {
+        self.overrides
+            .get(fq_path)
+            .cloned()
+            .or_else(|| self.default.clone())
+    }
+}
+
+/// Returns true if the configured `path` matches the fully-qualified `fq_path`
+///
+/// A leading-dot `path` matches `fq_path` itself, or anything nested beneath it (so
+/// `.mypackage.MyMessage` matches both the message and `.mypackage.MyMessage.my_field`),
+/// preserving the `Builder`'s existing "select a whole package/message" behaviour; the
+/// root path `.` matches everything. A dotless `path` is suffix-matched against the
+/// `.`-delimited segments of `fq_path`
+pub(crate) fn path_matches(path: &str, fq_path: &str) -> bool {
+    let fq_path = fq_path.trim_start_matches('.');
+
+    if let Some(absolute) = path.strip_prefix('.') {
+        return absolute.is_empty()
+            || fq_path == absolute
+            || fq_path.starts_with(&format!("{}.", absolute));
+    }
+
+    fq_path == path || fq_path.ends_with(&format!(".{}", path))
+}
+
+/// Returns true if `path` is matched as a dotless suffix rather than a leading-dot
+/// absolute path - used to rank suffix matches above absolute/prefix matches in
+/// [`PathMap::get`], matching `prost-build`'s `path::PathMap`, which always prefers a
+/// suffix match over a prefix match irrespective of either path's length
+fn is_suffix_path(path: &str) -> bool {
+    !path.starts_with('.')
+}
+
+/// Returns true if `path` matches `fq_path` in full - as opposed to matching only a
+/// suffix or an ancestor of it - used to rank an exact match above every other kind in
+/// [`PathMap::get`], matching `prost-build`'s own `path::PathMap`, which always tries
+/// the fully-qualified path itself before falling back to suffix and prefix matches
+fn is_exact_path(path: &str, fq_path: &str) -> bool {
+    path.trim_start_matches('.') == fq_path.trim_start_matches('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn absolute_path_matches_self_and_descendants() {
+        let mut map = PathMap::default();
+        map.insert_all([".mypackage.MyMessage"], true);
+
+        assert!(map.matches(".mypackage.MyMessage"));
+        assert!(map.matches(".mypackage.MyMessage.my_field"));
+        assert!(!map.matches(".mypackage.other.MyMessage"));
+        assert!(!map.matches(".mypackage.MyMessageOther"));
+    }
+
+    #[test]
+    fn dotless_path_suffix_matches() {
+        let mut map = PathMap::default();
+        map.insert_all(["MyMessage.my_field"], true);
+
+        assert!(map.matches(".mypackage.MyMessage.my_field"));
+        assert!(map.matches(".otherpackage.nested.MyMessage.my_field"));
+        assert!(!map.matches(".mypackage.MyMessage.other_field"));
+    }
+
+    #[test]
+    fn most_specific_match_wins() {
+        let mut map = PathMap::default();
+        map.insert_all([".mypackage"], 1);
+        map.insert_all([".mypackage.MyMessage"], 2);
+        map.insert_all([".mypackage.MyMessage.my_field"], 3);
+
+        assert_eq!(map.get(".mypackage.MyMessage.my_field"), Some(&3));
+        assert_eq!(map.get(".mypackage.MyMessage.other_field"), Some(&2));
+        assert_eq!(map.get(".mypackage.OtherMessage"), Some(&1));
+    }
+
+    #[test]
+    fn suffix_match_wins_over_longer_prefix_match() {
+        let mut map = PathMap::default();
+        map.insert_all([".abcd"], 1);
+        map.insert_all(["xyz"], 2);
+
+        assert_eq!(map.get(".abcd.xyz"), Some(&2));
+    }
+
+    #[test]
+    fn exact_match_wins_over_suffix_match() {
+        let mut map = PathMap::default();
+        map.insert_all([".a.b.c.d"], 4);
+        map.insert_all(["c.d"], 3);
+
+        assert_eq!(map.get(".a.b.c.d"), Some(&4));
+    }
+
+    #[test]
+    fn path_option_override_wins_over_default() {
+        let mut option = PathOption::default();
+        assert_eq!(option.resolve(".mypackage.MyMessage"), None);
+
+        option.set_default(false);
+        option.set_for([".mypackage.MyMessage"], true);
+
+        assert_eq!(option.resolve(".mypackage.MyMessage"), Some(true));
+        assert_eq!(option.resolve(".mypackage.OtherMessage"), Some(false));
+    }
+}
@@ -0,0 +1,135 @@
+//! Resolves fully-qualified protobuf type names to the Rust paths generated by `prost-build`
+
+use std::collections::HashSet;
+
+use crate::descriptor::Package;
+
+/// Resolves protobuf type and enum variant names to the Rust identifiers `prost-build`
+/// would have generated for them
+#[derive(Debug)]
+pub struct Resolver<'a> {
+    extern_paths: &'a [(String, String)],
+    package: &'a Package,
+    retain_enum_prefix: bool,
+    strip_enum_vairant_prefix_and_to_lowercase: bool,
+    enum_prefixes_to_keep: &'a HashSet<String>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(
+        extern_paths: &'a [(String, String)],
+        package: &'a Package,
+        retain_enum_prefix: bool,
+        strip_enum_vairant_prefix_and_to_lowercase: bool,
+        enum_prefixes_to_keep: &'a HashSet<String>,
+    ) -> Self {
+        Self {
+            extern_paths,
+            package,
+            retain_enum_prefix,
+            strip_enum_vairant_prefix_and_to_lowercase,
+            enum_prefixes_to_keep,
+        }
+    }
+
+    /// Returns the Rust path `prost-build` would generate for the protobuf type at `path`,
+    /// relative to the module generated for [`Self::package`]
+    pub fn rust_type(&self, path: &str) -> String {
+        // Extern paths are configured fully-qualified, with a leading dot
+        let fq_path = format!(".{}", path.trim_start_matches('.'));
+        for (proto_path, rust_path) in self.extern_paths {
+            if let Some(remainder) = fq_path.strip_prefix(proto_path.as_str()) {
+                if remainder.is_empty() {
+                    return rust_path.clone();
+                }
+                if remainder.starts_with('.') {
+                    return format!("{}::{}", rust_path, to_upper_camel_path(remainder));
+                }
+            }
+        }
+
+        let package = self.package.to_string();
+        let path = path.trim_start_matches('.');
+        let relative = path.strip_prefix(&package).unwrap_or(path);
+        to_upper_camel_path(relative)
+    }
+
+    /// Returns the Rust variant name `prost-build` would generate for the protobuf enum
+    /// value `variant_name` belonging to the enum `enum_name`
+    pub fn rust_enum_variant(&self, enum_name: &str, variant_name: &str) -> String {
+        let upper = variant_name.to_ascii_uppercase();
+        let prefix = to_shouty_snake_case(enum_name);
+
+        let stripped = if !self.retain_enum_prefix && upper.starts_with(&prefix) {
+            upper[prefix.len()..].trim_start_matches('_')
+        } else {
+            upper.as_str()
+        };
+        let stripped = if stripped.is_empty() { upper.as_str() } else { stripped };
+
+        crate::escape::escape_ident(&to_upper_camel_case(&stripped.to_ascii_lowercase()))
+    }
+
+    /// Returns the lenient, snake-cased JSON enum name for `variant_name`, used by
+    /// `strip_enum_vairant_prefix_and_to_lowercase`
+    pub fn json_enum_variant(&self, enum_name: &str, variant_name: &str) -> String {
+        if !self.strip_enum_vairant_prefix_and_to_lowercase
+            || self.enum_prefixes_to_keep.contains(enum_name)
+        {
+            return variant_name.to_string();
+        }
+
+        let prefix = to_shouty_snake_case(enum_name);
+        let upper = variant_name.to_ascii_uppercase();
+        let stripped = upper
+            .strip_prefix(&prefix)
+            .map(|s| s.trim_start_matches('_'))
+            .unwrap_or(variant_name);
+
+        stripped.to_ascii_lowercase()
+    }
+}
+
+/// Converts a `.`-delimited proto path into a `::`-delimited Rust path: the type itself
+/// is UpperCamelCase, and each enclosing message becomes the snake_case module
+/// `prost-build` nests it in
+fn to_upper_camel_path(path: &str) -> String {
+    let segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
+    let (name, parents) = match segments.split_last() {
+        Some(split) => split,
+        None => return String::new(),
+    };
+    parents
+        .iter()
+        .map(|s| to_shouty_snake_case(s).to_ascii_lowercase())
+        .chain(std::iter::once(to_upper_camel_case(name)))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+fn to_upper_camel_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut capitalize = true;
+    for c in s.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if capitalize {
+            out.extend(c.to_uppercase());
+            capitalize = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn to_shouty_snake_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() && i != 0 {
+            out.push('_');
+        }
+        out.extend(c.to_uppercase());
+    }
+    out
+}
@@ -0,0 +1,106 @@
+//! Minimal protobuf wire-format primitives for reading specific fields directly out of
+//! raw serialized bytes, by field number, without fully decoding the surrounding message
+//!
+//! A `prost::Message`-derived decode (e.g. into [`prost_types::FieldOptions`]) silently
+//! discards any field number it wasn't generated to know about - which is exactly what
+//! happens to a `.proto` custom option extension once `protoc` has resolved it, since
+//! `prost_types`' descriptor types have no idea those extension numbers exist. Reading
+//! the raw bytes by field number sidesteps that: on the wire, a resolved extension is
+//! indistinguishable from an ordinary field with that number
+
+/// The raw value of one field read off the wire, not yet interpreted as any particular
+/// protobuf type
+enum RawValue<'a> {
+    Varint(u64),
+    LengthDelimited(&'a [u8]),
+}
+
+/// Iterates the top-level fields of a single serialized protobuf message, in wire order
+///
+/// Fixed32/Fixed64 fields are skipped (none of the descriptor messages this module reads
+/// have any), and an unparseable tail (truncated varint, truncated length-delimited
+/// payload, or a group wire type) simply ends iteration rather than panicking
+fn fields(mut buf: &[u8]) -> impl Iterator<Item = (u32, RawValue<'_>)> {
+    std::iter::from_fn(move || loop {
+        let (key, rest) = read_varint(buf)?;
+        buf = rest;
+        let field_number = (key >> 3) as u32;
+        let wire_type = key & 0x7;
+
+        let value = match wire_type {
+            0 => {
+                let (value, rest) = read_varint(buf)?;
+                buf = rest;
+                RawValue::Varint(value)
+            }
+            1 => {
+                buf = split_at_checked(buf, 8)?.1;
+                continue;
+            }
+            2 => {
+                let (len, rest) = read_varint(buf)?;
+                let (payload, rest) = split_at_checked(rest, len as usize)?;
+                buf = rest;
+                RawValue::LengthDelimited(payload)
+            }
+            5 => {
+                buf = split_at_checked(buf, 4)?.1;
+                continue;
+            }
+            _ => return None,
+        };
+
+        return Some((field_number, value));
+    })
+}
+
+/// Splits `buf` into `(&buf[..mid], &buf[mid..])`, or `None` if `buf` is shorter than
+/// `mid` bytes
+fn split_at_checked(buf: &[u8], mid: usize) -> Option<(&[u8], &[u8])> {
+    (mid <= buf.len()).then(|| buf.split_at(mid))
+}
+
+/// Reads a base-128 varint off the front of `buf`, returning the decoded value and the
+/// remaining bytes
+fn read_varint(buf: &[u8]) -> Option<(u64, &[u8])> {
+    let mut value = 0u64;
+    for (i, &byte) in buf.iter().enumerate() {
+        value |= u64::from(byte & 0x7f) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Some((value, &buf[i + 1..]));
+        }
+        if i == 9 {
+            break;
+        }
+    }
+    None
+}
+
+/// Returns the raw payload of every length-delimited field numbered `tag` in `buf`, in
+/// wire order
+pub(crate) fn length_delimited_fields(buf: &[u8], tag: u32) -> impl Iterator<Item = &[u8]> {
+    fields(buf).filter_map(move |(field_number, value)| match value {
+        RawValue::LengthDelimited(payload) if field_number == tag => Some(payload),
+        _ => None,
+    })
+}
+
+/// Returns the value of the last occurrence of the length-delimited string field
+/// numbered `tag` in `buf`, if any and if valid UTF-8 (protobuf's own wire format
+/// doesn't otherwise guarantee it, but every field this module reads is a `string`)
+pub(crate) fn string_field(buf: &[u8], tag: u32) -> Option<&str> {
+    length_delimited_fields(buf, tag)
+        .last()
+        .and_then(|bytes| std::str::from_utf8(bytes).ok())
+}
+
+/// Returns the value of the last occurrence of the varint-encoded boolean field
+/// numbered `tag` in `buf`, if any
+pub(crate) fn bool_field(buf: &[u8], tag: u32) -> Option<bool> {
+    fields(buf)
+        .filter_map(|(field_number, value)| match value {
+            RawValue::Varint(v) if field_number == tag => Some(v != 0),
+            _ => None,
+        })
+        .last()
+}